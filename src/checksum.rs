@@ -0,0 +1,63 @@
+/// SHA-256 integrity checksums for `.dtf` files.
+///
+/// Each `<name>.dtf` gets a sidecar `<name>.dtf.sha256` holding the hex
+/// digest of the file's current on-disk bytes (ciphertext when at-rest
+/// encryption is on, plaintext otherwise). `Store::load` recomputes and
+/// compares the digest before trusting a file's contents.
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+
+use sha2::{Digest, Sha256};
+
+/// Path of the sidecar checksum file for a given `.dtf` path.
+fn sidecar_path(dtf_path: &str) -> String {
+    format!("{}.sha256", dtf_path)
+}
+
+/// Stream `path` through SHA-256 in fixed-size chunks and return the
+/// lowercase hex digest, without holding the whole file in memory at once.
+pub fn hex_digest_of_file(path: &str) -> io::Result<String> {
+    let mut f = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = f.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Hash `data` that's already in memory, so a caller that just produced
+/// these bytes (e.g. freshly encrypted ciphertext) doesn't have to re-read
+/// the file it wrote them to just to checksum it.
+pub fn hex_digest_of_bytes(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Write `digest` to the sidecar file for `dtf_path`.
+pub fn write_sidecar(dtf_path: &str, digest: &str) -> io::Result<()> {
+    let mut f = File::create(sidecar_path(dtf_path))?;
+    f.write_all(digest.as_bytes())
+}
+
+/// Read the stored digest for `dtf_path`, if a sidecar exists.
+pub fn read_sidecar(dtf_path: &str) -> Option<String> {
+    fs::read_to_string(sidecar_path(dtf_path)).ok().map(|s| s.trim().to_owned())
+}
+
+/// Does `dtf_path` currently have a sidecar checksum that matches its contents?
+/// Returns `false` if the file or sidecar is missing, or on any I/O error.
+pub fn verify(dtf_path: &str) -> bool {
+    match (hex_digest_of_file(dtf_path), read_sidecar(dtf_path)) {
+        (Ok(actual), Some(expected)) => actual == expected,
+        _ => false
+    }
+}
@@ -0,0 +1,150 @@
+/// ChaCha20 streaming encryption for DTF files at rest.
+///
+/// An encrypted file on disk is laid out as:
+///
+///     [6-byte magic "DTFCHA"][12-byte nonce][ChaCha20(plaintext)]
+///
+/// Because ChaCha20 is a stream cipher, encrypting is a byte-for-byte XOR
+/// against the keystream, so a fresh random nonce per file is enough to
+/// keep two writes of the same store from reusing a keystream. The key is
+/// derived from a passphrase named in `conf` under `encryption_key`, and
+/// `encrypt`/`decrypt` refuse to run unless `encryption_enabled` is `true`.
+
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+
+use conf;
+use dtf;
+
+use chacha20::ChaCha20;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+static MAGIC : &[u8; 6] = b"DTFCHA";
+static NONCE_LEN : usize = 12;
+
+/// Why an encrypt/decrypt attempt failed.
+#[derive(Debug)]
+pub enum CryptoError {
+    /// `encryption_enabled` is not set to `true` in `conf`.
+    Disabled,
+    /// Encryption is enabled but no `encryption_key` is configured.
+    MissingKey,
+    /// File doesn't start with the expected magic marker, or is too short to hold it plus a nonce.
+    BadMagic,
+    Io(io::Error)
+}
+
+impl From<io::Error> for CryptoError {
+    fn from(e: io::Error) -> Self {
+        CryptoError::Io(e)
+    }
+}
+
+/// Is at-rest encryption turned on in the config file?
+pub fn is_enabled() -> bool {
+    conf::get_config().get("encryption_enabled").map_or(false, |v| v == "true")
+}
+
+/// Derive a 256-bit key from the configured passphrase/key path.
+fn derive_key() -> Result<[u8; 32], CryptoError> {
+    let configs = conf::get_config();
+    let passphrase = configs.get("encryption_key").ok_or(CryptoError::MissingKey)?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(passphrase.as_bytes());
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&hasher.finalize());
+    Ok(key)
+}
+
+/// Encrypt `plaintext` under a freshly generated nonce, returning
+/// `magic || nonce || ciphertext`. Fails loudly if encryption isn't
+/// enabled or no key is configured, rather than silently writing garbage.
+pub fn encrypt(plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if !is_enabled() {
+        return Err(CryptoError::Disabled);
+    }
+    let key = derive_key()?;
+
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut cipher = ChaCha20::new(&key.into(), &nonce.into());
+    let mut buf = plaintext.to_vec();
+    cipher.apply_keystream(&mut buf);
+
+    let mut out = Vec::with_capacity(MAGIC.len() + NONCE_LEN + buf.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&buf);
+    Ok(out)
+}
+
+/// Reverse of `encrypt`: checks the magic marker, pulls the stored nonce
+/// back out, and reinitializes the cipher with it before decrypting.
+pub fn decrypt(data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if !is_enabled() {
+        return Err(CryptoError::Disabled);
+    }
+    if data.len() < MAGIC.len() + NONCE_LEN || &data[..MAGIC.len()] != &MAGIC[..] {
+        return Err(CryptoError::BadMagic);
+    }
+    let key = derive_key()?;
+    let nonce = &data[MAGIC.len()..MAGIC.len() + NONCE_LEN];
+    let ciphertext = &data[MAGIC.len() + NONCE_LEN..];
+
+    let mut cipher = ChaCha20::new(&key.into(), nonce.into());
+    let mut buf = ciphertext.to_vec();
+    cipher.apply_keystream(&mut buf);
+    Ok(buf)
+}
+
+/// Encrypt `plaintext` and write it to `path`, replacing any existing file.
+pub fn encrypt_to_file(path: &str, plaintext: &[u8]) -> Result<(), CryptoError> {
+    let encoded = encrypt(plaintext)?;
+    let mut f = File::create(path)?;
+    f.write_all(&encoded)?;
+    Ok(())
+}
+
+/// Read `path` back and decrypt it to plaintext bytes.
+pub fn decrypt_from_file(path: &str) -> Result<Vec<u8>, CryptoError> {
+    let mut f = File::open(path)?;
+    let mut data = Vec::new();
+    f.read_to_end(&mut data)?;
+    decrypt(&data)
+}
+
+/// Path of the sidecar holding `dtf::get_size`'s header count for a plaintext
+/// DTF file, recorded before the file was encrypted in place.
+fn size_sidecar_path(path: &str) -> String {
+    format!("{}.size", path)
+}
+
+/// Record `size` (the plaintext's `dtf::get_size` header count) for `path`,
+/// since `dtf::get_size` can't be run on `path` itself once it's ciphertext.
+pub fn write_size_sidecar(path: &str, size: u64) -> io::Result<()> {
+    let mut f = File::create(size_sidecar_path(path))?;
+    f.write_all(size.to_string().as_bytes())
+}
+
+/// Read back the size sidecar written by `write_size_sidecar`, if any.
+pub fn read_size_sidecar(path: &str) -> Option<u64> {
+    std::fs::read_to_string(size_sidecar_path(path)).ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+}
+
+/// `dtf::get_size`, but aware that `path` may hold ciphertext: when at-rest
+/// encryption is on, `dtf::get_size` would parse the `DTFCHA` magic/nonce as
+/// a bogus DTF header, so read the size sidecar recorded at encrypt time
+/// instead of the file itself.
+pub fn get_size(path: &str) -> u64 {
+    if is_enabled() {
+        read_size_sidecar(path).unwrap_or(0)
+    } else {
+        dtf::get_size(path)
+    }
+}
@@ -7,9 +7,19 @@ static HELP_STR : &str = "PING, INFO, USE [db], CREATE [db],
 ADD [ts],[seq],[is_trade],[is_bid],[price],[size];
 BULKADD ...; DDAKLUB
 FLUSH, FLUSHALL, GETALL, GET [count], CLEAR
+SETMAXMEM [bytes]
+VERIFY [db], VERIFYALL
+GETRAW [count], GETRAW64 [count]
+RELOADCONF
 ";
 
+/// Fallback memory budget when `max_in_memory_bytes` is absent from the config file.
+static DEFAULT_MAX_IN_MEMORY_BYTES : u64 = 1_000_000_000;
+
 use conf;
+use crypto;
+use checksum;
+use base64;
 
 use std::collections::HashMap;
 use std::io::{Read, Write};
@@ -19,6 +29,9 @@ use std::path::Path;
 use std::thread;
 use std::str;
 use std::fs;
+use std::mem;
+use std::sync::{Arc, RwLock, OnceLock};
+use std::time::Duration;
 
 use dtf;
 
@@ -47,6 +60,11 @@ struct Store {
     in_memory: bool,
     size: u64,
     v: Vec<dtf::Update>,
+    /// Whether the on-disk file currently has a checksum sidecar matching its contents,
+    /// as last recorded by `init_dbs`/`merge_dbs` or a `load`.
+    checksum_ok: bool,
+    /// Whether `v` holds updates not yet written to disk by a successful `flush`.
+    dirty: bool,
 }
 
 impl Store {
@@ -54,58 +72,211 @@ impl Store {
     fn add(&mut self, new_vec : dtf::Update) {
         self.v.push(new_vec);
         self.size += 1;
+        self.dirty = true;
     }
 
     /// Map vec of updates into JSON lists of objects
-    /// 
+    ///
     /// example:
     /// [{"ts":1505177459.658,"seq":139010,"is_trade":true,"is_bid":true,"price":0.0703629,"size":7.6506424}]
     fn to_string(&self, count:i32) -> String {
         let objects : Vec<String> = match count {
-            -1 => self.v.clone().into_iter().map(|up| up.to_json()).collect(),
-            n => self.v.clone().into_iter().take(n as usize).map(|up| up.to_json()).collect()
+            -1 => self.v.iter().map(|up| up.to_json()).collect(),
+            n => self.v.iter().take(n as usize).map(|up| up.to_json()).collect()
         };
 
         format!("[{}]\n", objects.join(","))
     }
 
+    /// Serialize the selected updates using `dtf`'s own compact on-wire
+    /// encoding instead of JSON. `dtf::encode` is file-path only, so the
+    /// selected updates are encoded into a scratch file and read back as
+    /// bytes; the scratch file is removed immediately afterward.
+    fn to_raw(&self, count: i32) -> Vec<u8> {
+        let selected : Vec<dtf::Update> = match count {
+            -1 => self.v.clone(),
+            n => self.v.iter().take(n as usize).cloned().collect()
+        };
+
+        let scratch = format!("{}/{}.raw.tmp", self.folder, self.name);
+        dtf::encode(&scratch, &self.name /*XXX*/, &selected);
+        let bytes = fs::read(&scratch).unwrap_or_default();
+        let _ = fs::remove_file(&scratch);
+        bytes
+    }
+
     /// write items stored in memory into file
     /// If file exists, use append which only appends a filtered set of updates whose timestamp is larger than the old timestamp
     /// If file doesn't exists, simply encode.
-    /// 
+    ///
     /// TODO: Need to figure out how to specify symbol (and exchange name).
-    fn flush(&self) -> Option<bool> {
+    ///
+    /// When `encryption_enabled` is set in `conf`, the file on disk holds
+    /// ciphertext, so `dtf::encode`/`dtf::append` instead write through a
+    /// plaintext scratch file that gets encrypted into place afterwards.
+    fn flush(&mut self) -> Option<bool> {
         let fname = format!("{}/{}.dtf", self.folder, self.name);
-        if Path::new(&fname).exists() {
-            dtf::append(&fname, &self.v);
-            return Some(true);
+        let ok = if crypto::is_enabled() {
+            self.flush_encrypted(&fname)
+        } else {
+            if Path::new(&fname).exists() {
+                dtf::append(&fname, &self.v);
+            } else {
+                dtf::encode(&fname, &self.name /*XXX*/, &self.v);
+            }
+            // `dtf::encode`/`append` hand back no buffer, so this is the one
+            // path that still has to re-read the file it just wrote.
+            let digest = checksum::hex_digest_of_file(&fname)
+                .and_then(|digest| checksum::write_sidecar(&fname, &digest));
+            if let Err(e) = digest {
+                eprintln!("ERR: failed to write checksum for `{}`: {}", fname, e);
+            }
+            Some(true)
+        };
+
+        if ok.is_some() {
+            self.dirty = false;
+        }
+        ok
+    }
+
+    /// `dtf::encode`/`dtf::append` only expose a file-path API (no in-memory
+    /// buffer variant), so this writes plaintext through a scratch file that
+    /// gets removed immediately after use. The ciphertext itself is hashed
+    /// straight out of memory, so unlike the plaintext path above this one
+    /// never re-reads `fname` just to checksum it.
+    fn flush_encrypted(&self, fname: &str) -> Option<bool> {
+        let scratch = format!("{}.plain.tmp", fname);
+        if Path::new(fname).exists() {
+            let decrypted = crypto::decrypt_from_file(fname)
+                .and_then(|plain| fs::write(&scratch, plain).map_err(crypto::CryptoError::from));
+            if let Err(e) = decrypted {
+                eprintln!("ERR: failed to decrypt `{}` for append: {:?}", fname, e);
+                return None;
+            }
+            dtf::append(&scratch, &self.v);
+        } else {
+            dtf::encode(&scratch, &self.name /*XXX*/, &self.v);
+        }
+
+        let plaintext = match fs::read(&scratch) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("ERR: failed to read scratch file `{}`: {}", scratch, e);
+                let _ = fs::remove_file(&scratch);
+                return None;
+            }
+        };
+        // `dtf::get_size` can only read the header of a plaintext DTF file,
+        // so record it now off the scratch file while it's still plaintext;
+        // once `fname` holds ciphertext, `crypto::get_size` reads this instead.
+        if let Err(e) = crypto::write_size_sidecar(fname, dtf::get_size(&scratch)) {
+            eprintln!("ERR: failed to write size sidecar for `{}`: {}", fname, e);
+        }
+
+        let ciphertext = match crypto::encrypt(&plaintext) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("ERR: failed to encrypt `{}`: {:?}", fname, e);
+                let _ = fs::remove_file(&scratch);
+                return None;
+            }
+        };
+        let digest = checksum::hex_digest_of_bytes(&ciphertext);
+        if let Err(e) = checksum::write_sidecar(fname, &digest) {
+            eprintln!("ERR: failed to write checksum for `{}`: {}", fname, e);
+        }
+        let result = fs::write(fname, &ciphertext);
+        let _ = fs::remove_file(&scratch);
+        match result {
+            Ok(()) => Some(true),
+            Err(e) => {
+                eprintln!("ERR: failed to write `{}`: {}", fname, e);
+                None
+            }
         }
-        dtf::encode(&fname, &self.name /*XXX*/, &self.v);
-        Some(true)
     }
 
     /// load items from dtf file
+    ///
+    /// Recomputes the file's checksum first; on mismatch, refuses to mark
+    /// the store `in_memory` and leaves `checksum_ok` false instead of
+    /// loading (possibly corrupt) data.
     fn load(&mut self) {
         let fname = format!("{}/{}.dtf", self.folder, self.name);
-        if Path::new(&fname).exists() {
-            self.v = dtf::decode(&fname);
-            self.size = self.v.len() as u64;
-            self.in_memory = true;
+        if !Path::new(&fname).exists() {
+            return;
+        }
+
+        if let Some(expected) = checksum::read_sidecar(&fname) {
+            match checksum::hex_digest_of_file(&fname) {
+                Ok(actual) if actual == expected => self.checksum_ok = true,
+                Ok(_) => {
+                    eprintln!("ERR: checksum mismatch for store `{}`, refusing to load", self.name);
+                    self.checksum_ok = false;
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("ERR: failed to checksum `{}`: {}", fname, e);
+                    self.checksum_ok = false;
+                    return;
+                }
+            }
         }
+
+        if crypto::is_enabled() {
+            if let Err(e) = self.load_encrypted(&fname) {
+                eprintln!("ERR: failed to load encrypted store `{}`: {:?}", self.name, e);
+            }
+            return;
+        }
+        self.v = dtf::decode(&fname);
+        self.size = self.v.len() as u64;
+        self.in_memory = true;
+    }
+
+    /// `dtf::decode` is file-path only, so this decrypts into a plaintext
+    /// scratch file, hands that to `dtf::decode`, then removes the scratch file.
+    fn load_encrypted(&mut self, fname: &str) -> Result<(), crypto::CryptoError> {
+        let plaintext = crypto::decrypt_from_file(fname)?;
+        let scratch = format!("{}.plain.tmp", fname);
+        fs::write(&scratch, &plaintext)?;
+        self.v = dtf::decode(&scratch);
+        self.size = self.v.len() as u64;
+        self.in_memory = true;
+        let _ = fs::remove_file(&scratch);
+        Ok(())
     }
 
     /// load size from file
     fn load_size_from_file(&mut self) {
-        let header_size = dtf::get_size(&format!("{}/{}", self.folder, self.name));
-        self.size = header_size;
+        let fname = format!("{}/{}.dtf", self.folder, self.name);
+        self.size = crypto::get_size(&fname);
     }
 
-    /// clear the vector. toggle in_memory. update size
+    /// Flush any unwritten updates first, then clear the vector, toggle
+    /// `in_memory`, and update size. Flushing first means `clear` (and the
+    /// eviction path in `enforce_memory_budget`, which calls it) never
+    /// discards updates that were never persisted.
     fn clear(&mut self) {
+        if self.dirty {
+            if self.flush().is_none() {
+                eprintln!("ERR: failed to flush `{}` before clearing, keeping it resident", self.name);
+                return;
+            }
+        }
         self.v.clear();
         self.in_memory = false;
         self.load_size_from_file();
     }
+
+    /// Approximate number of bytes resident in memory for this store.
+    /// Counts `v` regardless of `in_memory`, since a store filled via
+    /// `ADD`/`BULKADD` but never `load()`-ed (or not yet flushed) still
+    /// holds real updates in memory and must count against the budget.
+    fn byte_size(&self) -> u64 {
+        (self.v.len() * mem::size_of::<dtf::Update>()) as u64
+    }
 }
 
 
@@ -114,7 +285,57 @@ struct State {
     is_adding: bool,
     store: HashMap<String, Store>,
     current_store_name: String,
-    dtf_folder: String
+    dtf_folder: String,
+    /// Memory budget (in bytes) for resident stores, read from `conf` at startup
+    /// and adjustable at runtime via `SETMAXMEM`.
+    max_in_memory_bytes: u64,
+    /// Recency list of resident store names, most-recently-touched first.
+    lru: Vec<String>,
+    /// Number of stores evicted via `clear()` to stay under `max_in_memory_bytes`.
+    eviction_count: u64
+}
+
+impl State {
+    /// Bump `name` to the front of the LRU list, then evict from the back
+    /// until the resident byte total is back under budget.
+    fn touch(&mut self, name: &str) {
+        self.lru.retain(|n| n != name);
+        self.lru.insert(0, name.to_owned());
+        self.enforce_memory_budget();
+    }
+
+    /// Total bytes currently resident across all stores.
+    fn resident_bytes(&self) -> u64 {
+        self.store.values().map(|store| store.byte_size()).sum()
+    }
+
+    /// Evict least-recently-used stores (via `Store::clear`) until under budget.
+    /// Never evicts `current_store_name`.
+    fn enforce_memory_budget(&mut self) {
+        while self.resident_bytes() > self.max_in_memory_bytes {
+            let current = self.current_store_name.clone();
+            let victim = self.lru.iter().rev()
+                .find(|name| **name != current && self.store.get(*name).map_or(false, |s| !s.v.is_empty()))
+                .cloned();
+            match victim {
+                Some(name) => {
+                    // `clear` keeps the updates if its flush fails.
+                    let evicted = match self.store.get_mut(&name) {
+                        Some(store) => {
+                            store.clear();
+                            store.v.is_empty()
+                        }
+                        None => false
+                    };
+                    self.lru.retain(|n| n != &name);
+                    if evicted {
+                        self.eviction_count += 1;
+                    }
+                }
+                None => break
+            }
+        }
+    }
 }
 
 /// Parses a line that looks like 
@@ -155,14 +376,33 @@ fn parse_line(string : &str) -> Option<dtf::Update> {
     Some(u)
 }
 
-fn gen_response(string : &str, state: &mut State) -> Option<String> {
+/// Frame a raw payload as `[4-byte LE length header][payload]`, so GETRAW
+/// and GETRAW64 ship the exact same bytes over the wire (one written raw to
+/// the socket, the other base64-encoded) rather than disagreeing on framing.
+fn framed_raw(bytes: Vec<u8>) -> Vec<u8> {
+    let len = bytes.len() as u32;
+    let mut framed = Vec::with_capacity(4 + bytes.len());
+    framed.extend_from_slice(&len.to_le_bytes());
+    framed.extend_from_slice(&bytes);
+    framed
+}
+
+fn gen_response(string : &str, state: &mut State, stream: &mut TcpStream) -> Option<String> {
     match string {
         "" => Some("".to_owned()),
         "PING" => Some("PONG.\n".to_owned()),
         "HELP" => Some(HELP_STR.to_owned()),
         "INFO" => {
+            // Keep the top-level response the array shape existing clients
+            // already parse; attach the new totals to each store entry
+            // instead of wrapping the array in an object.
+            let total_resident_bytes = state.resident_bytes();
+            let evictions = state.eviction_count;
             let info_vec : Vec<String> = state.store.values().map(|store| {
-                format!(r#"{{"name": "{}", "in_memory": {}, "count": {}}}"#, store.name, store.in_memory, store.size)
+                format!(
+                    r#"{{"name": "{}", "in_memory": {}, "count": {}, "total_resident_bytes": {}, "evictions": {}}}"#,
+                    store.name, store.in_memory, store.size, total_resident_bytes, evictions
+                )
             }).collect();
 
             Some(format!("[{}]\n", info_vec.join(", ")))
@@ -176,7 +416,10 @@ fn gen_response(string : &str, state: &mut State) -> Option<String> {
             Some("1\n".to_owned())
         },
         "GETALL" => {
-            Some(state.store.get_mut(&state.current_store_name).unwrap().to_string(-1))
+            let name = state.current_store_name.clone();
+            let resp = state.store.get_mut(&name).unwrap().to_string(-1);
+            state.touch(&name);
+            Some(resp)
         },
         "CLEAR" => {
             let current_store = state.store.get_mut(&state.current_store_name).expect("KEY IS NOT IN HASHMAP");
@@ -189,16 +432,46 @@ fn gen_response(string : &str, state: &mut State) -> Option<String> {
             }
             Some("1\n".to_owned())
         },
+        "VERIFYALL" => {
+            let results : Vec<String> = state.store.values().map(|store| {
+                let fname = format!("{}/{}.dtf", store.folder, store.name);
+                format!(r#"{{"name": "{}", "ok": {}}}"#, store.name, checksum::verify(&fname))
+            }).collect();
+            Some(format!("[{}]\n", results.join(", ")))
+        },
+        "RELOADCONF" => {
+            // Compare against this connection's own `dtf_folder`, not the prior
+            // value of the global snapshot: the background watcher may have
+            // already swapped the snapshot in, which would make the two global
+            // readings equal and silently skip the rescan this connection needs.
+            let (_, new_folder) = reload_config();
+            state.max_in_memory_bytes = get_max_in_memory_bytes();
+            let mut new_count = 0;
+            if new_folder != state.dtf_folder {
+                state.dtf_folder = new_folder.clone();
+                create_dir_if_not_exist(&state.dtf_folder);
+                let folder = state.dtf_folder.clone();
+                new_count = merge_dbs(&folder, state);
+            }
+            Some(format!("{{\"folder\": \"{}\", \"new_stores\": {}}}\n", state.dtf_folder, new_count))
+        },
         "FLUSH" => {
             let current_store = state.store.get_mut(&state.current_store_name).expect("KEY IS NOT IN HASHMAP");
-            current_store.flush();
-            Some("1\n".to_owned())
+            match current_store.flush() {
+                Some(true) => Some("1\n".to_owned()),
+                _ => Some(format!("ERR failed to flush `{}`.\n", current_store.name))
+            }
         },
         "FLUSHALL" => {
-            for store in state.store.values() {
-                store.flush();
+            let failures : Vec<String> = state.store.values_mut()
+                .filter(|store| store.flush().is_none())
+                .map(|store| store.name.clone())
+                .collect();
+            if failures.is_empty() {
+                Some("1\n".to_owned())
+            } else {
+                Some(format!("ERR failed to flush: {}\n", failures.join(", ")))
             }
-            Some("1\n".to_owned())
         },
         _ => {
             // bulkadd and add
@@ -206,8 +479,10 @@ fn gen_response(string : &str, state: &mut State) -> Option<String> {
                 let parsed = parse_line(string);
                 match parsed {
                     Some(up) => {
-                        let current_store = state.store.get_mut(&state.current_store_name).expect("KEY IS NOT IN HASHMAP");
+                        let name = state.current_store_name.clone();
+                        let current_store = state.store.get_mut(&name).expect("KEY IS NOT IN HASHMAP");
                         current_store.add(up);
+                        state.touch(&name);
                     }
                     None => return None
                 }
@@ -218,13 +493,28 @@ fn gen_response(string : &str, state: &mut State) -> Option<String> {
                 let data_string : &str = &string[3..];
                 match parse_line(&data_string) {
                     Some(up) => {
-                        let current_store = state.store.get_mut(&state.current_store_name).expect("KEY IS NOT IN HASHMAP");
+                        let name = state.current_store_name.clone();
+                        let current_store = state.store.get_mut(&name).expect("KEY IS NOT IN HASHMAP");
                         current_store.v.push(up);
+                        current_store.dirty = true;
+                        state.touch(&name);
                     }
                     None => return None
                 }
                 Some("1\n".to_owned())
-            } else 
+            } else
+
+            if string.starts_with("SETMAXMEM ") {
+                let bytes_str : &str = &string[10..];
+                match bytes_str.parse::<u64>() {
+                    Ok(bytes) => {
+                        state.max_in_memory_bytes = bytes;
+                        state.enforce_memory_budget();
+                        Some(format!("SETMAXMEM to {} bytes.\n", bytes))
+                    }
+                    Err(_) => Some(format!("ERR invalid byte count `{}`.\n", bytes_str))
+                }
+            } else
 
             // db commands
             if string.starts_with("CREATE ") {
@@ -234,7 +524,9 @@ fn gen_response(string : &str, state: &mut State) -> Option<String> {
                     v: Vec::new(),
                     size: 0,
                     in_memory: false,
-                    folder: state.dtf_folder.clone()
+                    folder: state.dtf_folder.clone(),
+                    checksum_ok: true,
+                    dirty: false
                 });
                 Some(format!("Created DB `{}`.\n", &dbname))
             } else
@@ -245,18 +537,60 @@ fn gen_response(string : &str, state: &mut State) -> Option<String> {
                     state.current_store_name = dbname.to_owned();
                     let current_store = state.store.get_mut(&state.current_store_name).unwrap();
                     current_store.load();
+                    let name = state.current_store_name.clone();
+                    state.touch(&name);
                     Some(format!("SWITCHED TO DB `{}`.\n", &dbname))
                 } else {
                     Some(format!("ERR unknown DB `{}`.\n", &dbname))
                 }
             } else
 
+            // integrity
+            if string.starts_with("VERIFY ") {
+                let dbname : &str = &string[7..];
+                match state.store.get(dbname) {
+                    Some(store) => {
+                        let fname = format!("{}/{}.dtf", store.folder, store.name);
+                        let ok = checksum::verify(&fname);
+                        Some(format!(r#"[{{"name": "{}", "ok": {}}}]"# , dbname, ok) + "\n")
+                    }
+                    None => Some(format!("ERR unknown DB `{}`.\n", dbname))
+                }
+            } else
+
             // get
             if string.starts_with("GET ") {
                 let num : &str = &string[4..];
                 let count = num.parse::<i32>().unwrap();
-                let current_store = state.store.get_mut(&state.current_store_name).unwrap();
-                Some(current_store.to_string(count))
+                let name = state.current_store_name.clone();
+                let resp = state.store.get_mut(&name).unwrap().to_string(count);
+                state.touch(&name);
+                Some(resp)
+            } else
+
+            // raw binary get: [4-byte LE length header][packed updates]
+            if string.starts_with("GETRAW64 ") {
+                let num : &str = &string[9..];
+                let count = num.parse::<i32>().unwrap();
+                let name = state.current_store_name.clone();
+                let bytes = state.store.get(&name).unwrap().to_raw(count);
+                state.touch(&name);
+                Some(format!("{}\n", base64::encode(&framed_raw(bytes))))
+            } else
+
+            if string.starts_with("GETRAW ") {
+                let num : &str = &string[7..];
+                let count = num.parse::<i32>().unwrap();
+                let name = state.current_store_name.clone();
+                let bytes = state.store.get(&name).unwrap().to_raw(count);
+                state.touch(&name);
+                match stream.write_all(&framed_raw(bytes)) {
+                    Ok(()) => Some("".to_owned()),
+                    Err(e) => {
+                        eprintln!("ERR: failed to write GETRAW response: {}", e);
+                        None
+                    }
+                }
             }
 
             else {
@@ -266,14 +600,63 @@ fn gen_response(string : &str, state: &mut State) -> Option<String> {
     }
 }
 
+/// Holds the most recently loaded config, shared across connections so a
+/// `RELOADCONF` (or the background watcher) can swap it in atomically
+/// without the old values living frozen inside any one connection's State.
+static CONFIG_SNAPSHOT : OnceLock<RwLock<Arc<HashMap<String, String>>>> = OnceLock::new();
+
+fn config_cell() -> &'static RwLock<Arc<HashMap<String, String>>> {
+    CONFIG_SNAPSHOT.get_or_init(|| RwLock::new(Arc::new(conf::get_config())))
+}
+
+/// The current shared config snapshot.
+fn current_config() -> Arc<HashMap<String, String>> {
+    config_cell().read().unwrap().clone()
+}
+
+/// Re-read the config file from disk and atomically swap it into the shared
+/// snapshot. Returns the `dtf_folder` before and after, so callers can tell
+/// whether a rescan is needed.
+fn reload_config() -> (String, String) {
+    let old_folder = current_config().get("dtf_folder").cloned().unwrap_or_default();
+    let new_conf = conf::get_config();
+    let new_folder = new_conf.get("dtf_folder").cloned().unwrap_or_default();
+    *config_cell().write().unwrap() = Arc::new(new_conf);
+    (old_folder, new_folder)
+}
+
+/// Background thread that periodically re-reads the config file so that
+/// new connections (and anyone who sends `RELOADCONF`) see edits without
+/// restarting the server.
+fn spawn_config_watcher() {
+    thread::spawn(|| {
+        loop {
+            thread::sleep(Duration::from_secs(5));
+            let (old_folder, new_folder) = reload_config();
+            if old_folder != new_folder {
+                println!("config reload: dtf_folder changed `{}` -> `{}`", old_folder, new_folder);
+            }
+        }
+    });
+}
+
 /// Read config file and get folder name
 /// dtf_folder is a folder in which the dtf files live
 fn get_dtf_folder() -> String {
-    let configs = conf::get_config();
+    let configs = current_config();
     let dtf_folder = configs.get("dtf_folder").unwrap();
     dtf_folder.to_owned()
 }
 
+/// Read the in-memory budget from config, falling back to `DEFAULT_MAX_IN_MEMORY_BYTES`
+/// when `max_in_memory_bytes` is absent or unparseable.
+fn get_max_in_memory_bytes() -> u64 {
+    let configs = current_config();
+    configs.get("max_in_memory_bytes")
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_MAX_IN_MEMORY_BYTES)
+}
+
 fn create_dir_if_not_exist(dtf_folder : &str) {
     if !Path::new(dtf_folder).exists() {
         fs::create_dir(dtf_folder).unwrap();
@@ -289,18 +672,52 @@ fn init_dbs(dtf_folder : &str, state: &mut State) {
         let fname = fname_os.to_str().unwrap();
         if fname.ends_with(".dtf") {
             let name = Path::new(&fname_os).file_stem().unwrap().to_str().unwrap();
-            let header_size = dtf::get_size(&format!("{}/{}", dtf_folder, fname));
+            let full_path = format!("{}/{}", dtf_folder, fname);
+            let header_size = crypto::get_size(&full_path);
             state.store.insert(name.to_owned(), Store {
                 folder: dtf_folder.to_owned(),
                 name: name.to_owned(),
                 v: Vec::new(),
                 size: header_size,
-                in_memory: false
+                in_memory: false,
+                checksum_ok: checksum::verify(&full_path),
+                dirty: false
             });
         }
     }
 }
 
+/// Like `init_dbs`, but only registers files not already tracked in
+/// `state.store`, so a config reload never clobbers a store that has
+/// unflushed in-memory updates. Returns the number of newly registered stores.
+fn merge_dbs(dtf_folder : &str, state: &mut State) -> usize {
+    let mut new_count = 0;
+    for dtf_file in fs::read_dir(&dtf_folder).unwrap() {
+        let dtf_file = dtf_file.unwrap();
+        let fname_os = dtf_file.file_name();
+        let fname = fname_os.to_str().unwrap();
+        if fname.ends_with(".dtf") {
+            let name = Path::new(&fname_os).file_stem().unwrap().to_str().unwrap();
+            if state.store.contains_key(name) {
+                continue;
+            }
+            let full_path = format!("{}/{}", dtf_folder, fname);
+            let header_size = crypto::get_size(&full_path);
+            state.store.insert(name.to_owned(), Store {
+                folder: dtf_folder.to_owned(),
+                name: name.to_owned(),
+                v: Vec::new(),
+                size: header_size,
+                in_memory: false,
+                checksum_ok: checksum::verify(&full_path),
+                dirty: false
+            });
+            new_count += 1;
+        }
+    }
+    new_count
+}
+
 fn handle_client(mut stream: TcpStream) {
     let dtf_folder = get_dtf_folder();
     create_dir_if_not_exist(&dtf_folder);
@@ -310,14 +727,19 @@ fn handle_client(mut stream: TcpStream) {
         current_store_name: "default".to_owned(),
         is_adding: false,
         store: HashMap::new(),
-        dtf_folder: dtf_folder.to_owned()
+        dtf_folder: dtf_folder.to_owned(),
+        max_in_memory_bytes: get_max_in_memory_bytes(),
+        lru: Vec::new(),
+        eviction_count: 0
     };
     state.store.insert("default".to_owned(), Store {
         name: "default".to_owned(),
         v: Vec::new(),
         size: 0,
         in_memory: false,
-        folder: dtf_folder.to_owned()
+        folder: dtf_folder.to_owned(),
+        checksum_ok: true,
+        dirty: false
     });
 
     init_dbs(&dtf_folder, &mut state);
@@ -328,7 +750,7 @@ fn handle_client(mut stream: TcpStream) {
         if bytes_read == 0 { break }
         let req = str::from_utf8(&buf[..(bytes_read-1)]).unwrap();
 
-        let resp = gen_response(&req, &mut state);
+        let resp = gen_response(&req, &mut state, &mut stream);
         match resp {
             Some(str_resp) => {
                 stream.write(str_resp.as_bytes()).unwrap()
@@ -344,6 +766,8 @@ pub fn run_server() {
     let listener = TcpListener::bind(addr).unwrap();
     println!("Listening on addr: {}", addr);
 
+    spawn_config_watcher();
+
     for stream in listener.incoming() {
         let stream = stream.unwrap();
         thread::spawn(move || {